@@ -17,6 +17,10 @@ pub struct Config {
     mqtt_user: &'static str,
     #[default("1234")]
     mqtt_pass: &'static str,
+    #[default(0)]
+    mqtt_heartbeat_seconds: u64,
+    #[default(0)]
+    publish_deadline_seconds: u64,
 }
 
 fn main() {
@@ -62,5 +66,14 @@ fn main() {
         panic!("You need to set the MQTT credentials in `cfg.toml`!");
     }
 
+    // Publish deadline
+    // Otherwise we'd reboot while connected and healthy
+    if app_config.publish_deadline_seconds != 0
+        && (app_config.mqtt_heartbeat_seconds == 0
+            || app_config.mqtt_heartbeat_seconds >= app_config.publish_deadline_seconds)
+    {
+        panic!("mqtt_heartbeat_seconds must be set and less than publish_deadline_seconds!");
+    }
+
     embuild::espidf::sysenv::output();
 }
@@ -0,0 +1,70 @@
+use esp_idf_svc::hal::reset::restart;
+use log::warn;
+use std::{
+    sync::{Arc, Mutex},
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+/// Reboots the node if the broker hasn't acknowledged a publish in time.
+/// This catches cases where we think we're connected, but nothing is actually being delivered.
+#[derive(Clone)]
+pub struct PublishDeadline {
+    // None while paused
+    last_delivered: Arc<Mutex<Option<Instant>>>,
+}
+
+impl PublishDeadline {
+    pub fn new() -> Self {
+        Self {
+            last_delivered: Arc::new(Mutex::new(Some(Instant::now()))),
+        }
+    }
+
+    /// Checks the deadline on its own thread, so it still fires if the main loop has exited.
+    pub fn spawn_watchdog(&self, timeout: Duration) -> std::io::Result<()> {
+        let last_delivered = self.last_delivered.clone();
+        thread::Builder::new()
+            .name("publish-deadline".into())
+            .stack_size(4096)
+            .spawn(move || loop {
+                sleep(Duration::from_secs(1));
+                let elapsed = match *last_delivered
+                    .lock()
+                    .expect("Failed to lock publish deadline!?!")
+                {
+                    Some(time) => time.elapsed(),
+                    None => continue,
+                };
+                if elapsed > timeout {
+                    warn!(
+                        "No publish acknowledged in {}s, rebooting!",
+                        elapsed.as_secs()
+                    );
+                    restart();
+                }
+            })?;
+        Ok(())
+    }
+
+    pub fn delivered(&self) {
+        if let Ok(mut last_delivered) = self.last_delivered.lock() {
+            // Don't resume early if paused
+            if last_delivered.is_some() {
+                *last_delivered = Some(Instant::now());
+            }
+        }
+    }
+
+    pub fn pause(&self) {
+        if let Ok(mut last_delivered) = self.last_delivered.lock() {
+            *last_delivered = None;
+        }
+    }
+
+    pub fn resume(&self) {
+        if let Ok(mut last_delivered) = self.last_delivered.lock() {
+            *last_delivered = Some(Instant::now());
+        }
+    }
+}
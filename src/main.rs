@@ -1,6 +1,10 @@
-use std::{thread::sleep, time::Duration};
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
+use deadline::PublishDeadline;
 use esp_idf_svc::hal::prelude::Peripherals;
 use led::WS2812RMT;
 use log::{error, info};
@@ -9,6 +13,7 @@ use rgb::RGB8;
 use utils::{map_range, unix_seconds};
 use wifi::WiFi;
 
+mod deadline;
 mod led;
 mod mqtt;
 mod utils;
@@ -60,6 +65,10 @@ pub struct Config {
     mqtt_disconnected_timeout: u64,
     #[default(300)]
     mqtt_reconnect_timeout: u64,
+    #[default(0)]
+    mqtt_heartbeat_seconds: u64,
+    #[default(0)]
+    publish_deadline_seconds: u64,
 }
 
 struct State<'a> {
@@ -68,6 +77,8 @@ struct State<'a> {
     led: WS2812RMT<'a>,
     wifi_connected_time: Option<u32>,
     wifi_disconn_rssi_start: Option<u32>,
+    last_heartbeat: Instant,
+    publish_deadline: PublishDeadline,
 }
 
 fn main() -> Result<()> {
@@ -79,12 +90,19 @@ fn main() -> Result<()> {
 
     let mut peripherals = Peripherals::take().unwrap();
 
+    let publish_deadline = PublishDeadline::new();
+    if CONFIG.publish_deadline_seconds != 0 {
+        publish_deadline.spawn_watchdog(Duration::from_secs(CONFIG.publish_deadline_seconds))?;
+    }
+
     let mut state = State {
         wifi: WiFi::new(&mut peripherals, CONFIG)?,
-        mqtt: Mqtt::new(CONFIG)?,
+        mqtt: Mqtt::new(CONFIG, publish_deadline.clone())?,
         led: WS2812RMT::new(peripherals.pins.gpio8, peripherals.rmt.channel0)?,
         wifi_connected_time: None,
         wifi_disconn_rssi_start: None,
+        last_heartbeat: Instant::now(),
+        publish_deadline,
     };
 
     WiFi::set_max_tx_power(CONFIG.wifi_max_tx_power);
@@ -117,6 +135,7 @@ impl State<'_> {
             if self.mqtt.is_connected() {
                 match self.mqtt.publish() {
                     Ok(_) => {
+                        self.last_heartbeat = Instant::now();
                         self.set_led(CLR_MQTT_PUBLISHED);
                     }
                     Err(err) => return Err(err),
@@ -132,6 +151,16 @@ impl State<'_> {
             return Ok(());
         }
 
+        if CONFIG.mqtt_heartbeat_seconds != 0
+            && self.last_heartbeat.elapsed() >= Duration::from_secs(CONFIG.mqtt_heartbeat_seconds)
+        {
+            // Not fatal, it'll be retried next tick, and the publish deadline catches the rest.
+            match self.mqtt.publish() {
+                Ok(_) => self.last_heartbeat = Instant::now(),
+                Err(err) => error!("Failed to publish heartbeat: {:?}", err),
+            }
+        }
+
         let rssi = self.wifi.esp_wifi.wifi().get_rssi().unwrap_or(i32::MAX);
         info!("RSSI: {}dBm", rssi);
 
@@ -170,7 +199,10 @@ impl State<'_> {
             self.mqtt.disconnect();
         }
         self.set_led_with_brightness(CLR_SLEEPING, DEFAULT_BRIGHTNESS);
+        // We're intentionally not delivering anything while waiting
+        self.publish_deadline.pause();
         sleep(Duration::from_secs(CONFIG.mqtt_reconnect_timeout));
+        self.publish_deadline.resume();
         Ok(())
     }
 
@@ -1,6 +1,8 @@
-use crate::Config;
+use crate::{deadline::PublishDeadline, Config};
 use anyhow::{bail, Result};
-use esp_idf_svc::mqtt::client::{EspMqttClient, LwtConfiguration, MqttClientConfiguration, QoS};
+use esp_idf_svc::mqtt::client::{
+    EspMqttClient, EventPayload, LwtConfiguration, MqttClientConfiguration, QoS,
+};
 use log::info;
 use std::{
     sync::{Arc, Mutex},
@@ -30,10 +32,11 @@ pub struct Mqtt {
     topic: String,
     on_payload: &'static str,
     connection_status: Arc<Mutex<MqttConnectionStatus>>,
+    publish_deadline: PublishDeadline,
 }
 
 impl Mqtt {
-    pub fn new(config: Config) -> Result<Self> {
+    pub fn new(config: Config, publish_deadline: PublishDeadline) -> Result<Self> {
         let topic = format!(
             "{}/binary_sensor/{}/state",
             config.mqtt_discovery_prefix, config.mqtt_node
@@ -49,6 +52,7 @@ impl Mqtt {
             topic,
             on_payload: config.mqtt_on_payload,
             connection_status,
+            publish_deadline,
         })
     }
 
@@ -79,13 +83,19 @@ impl Mqtt {
         );
 
         let connection_status_clone = self.connection_status.clone();
+        let publish_deadline_clone = self.publish_deadline.clone();
         self.client = Some(EspMqttClient::new_cb(
             broker_url,
             &mqtt_config,
             move |event| {
-                let new_state = match event.payload().to_string() {
-                    s if s.starts_with("Connected") => true,
-                    s if s.starts_with("Disconnected") => false,
+                let new_state = match event.payload() {
+                    EventPayload::Connected(_) => true,
+                    EventPayload::Disconnected => false,
+                    // The broker acknowledged one of our QoS 1 publishes
+                    EventPayload::Published(_) => {
+                        publish_deadline_clone.delivered();
+                        return;
+                    }
                     _ => return,
                 };
 